@@ -4,36 +4,303 @@ extern crate semver;
 
 use std::env;
 use std::io::prelude::*;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::process::{exit, Command, Stdio};
 use std::borrow::Cow;
+use std::collections::HashSet;
 use clap::{App, AppSettings, SubCommand, Arg, ArgGroup, Values, OsValues, ArgMatches};
 use semver::{Version, VersionReq, Identifier, ReqParseError};
 
+/// A single entry of rustc's `--print cfg` output
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Cfg {
+    /// A bare cfg name, e.g. `unix`
+    Name(String),
+    /// A cfg key/value pair, e.g. `target_os = "linux"`
+    KeyPair(String, String),
+}
+
+/// A parsed cfg-expression, as accepted by `--cfg`
+enum CfgExpr {
+    Value(Cfg),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+impl CfgExpr {
+    /// Parses a cfg-expression string such as `all(unix, not(target_os = "macos"))`
+    fn parse(input: &str) -> Result<CfgExpr, String> {
+        CfgExprParser::new(input).parse()
+    }
+}
+
+/// Recursive-descent parser for the cfg-expression grammar used by `--cfg`
+struct CfgExprParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl CfgExprParser {
+    fn new(input: &str) -> CfgExprParser {
+        CfgExprParser {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<CfgExpr, String> {
+        let expr = try!(self.parse_expr());
+        self.skip_whitespace();
+        if self.pos != self.chars.len() {
+            return Err(format!("Unexpected trailing characters in cfg-expression at position {}",
+                                self.pos));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at position {} in cfg-expression", c, self.pos))
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(format!("Expected identifier at position {} in cfg-expression", start));
+        }
+        Ok(self.chars[start..self.pos].iter().cloned().collect())
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        try!(self.expect('"'));
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == '"' {
+                break;
+            }
+            self.pos += 1;
+        }
+        let value = self.chars[start..self.pos].iter().cloned().collect();
+        try!(self.expect('"'));
+        Ok(value)
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<CfgExpr>, String> {
+        let mut list = vec![try!(self.parse_expr())];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(',') {
+                self.pos += 1;
+                list.push(try!(self.parse_expr()));
+            } else {
+                break;
+            }
+        }
+        Ok(list)
+    }
+
+    fn parse_expr(&mut self) -> Result<CfgExpr, String> {
+        let ident = try!(self.parse_ident());
+        self.skip_whitespace();
+        match ident.as_str() {
+            "all" => {
+                try!(self.expect('('));
+                let list = try!(self.parse_list());
+                try!(self.expect(')'));
+                Ok(CfgExpr::All(list))
+            }
+            "any" => {
+                try!(self.expect('('));
+                let list = try!(self.parse_list());
+                try!(self.expect(')'));
+                Ok(CfgExpr::Any(list))
+            }
+            "not" => {
+                try!(self.expect('('));
+                let expr = try!(self.parse_expr());
+                try!(self.expect(')'));
+                Ok(CfgExpr::Not(Box::new(expr)))
+            }
+            _ => {
+                if self.peek() == Some('=') {
+                    self.pos += 1;
+                    let value = try!(self.parse_string());
+                    Ok(CfgExpr::Value(Cfg::KeyPair(ident, value)))
+                } else {
+                    Ok(CfgExpr::Value(Cfg::Name(ident)))
+                }
+            }
+        }
+    }
+}
+
+/// Parses a single line of `rustc --print cfg` output into a `Cfg`
+fn parse_cfg_line(line: &str) -> Cfg {
+    match line.find('=') {
+        Some(idx) => {
+            let key = &line[..idx];
+            let mut value = &line[idx + 1..];
+            if value.starts_with('"') && value.ends_with('"') && value.len() >= 2 {
+                value = &value[1..value.len() - 1];
+            }
+            Cfg::KeyPair(key.to_string(), value.to_string())
+        }
+        None => Cfg::Name(line.to_string()),
+    }
+}
+
+/// Is a string a valid `YYYY-MM-DD` date? Since ISO-8601 dates sort
+/// lexicographically, validated dates can be compared with plain string
+/// ordering.
+fn is_valid_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    bytes.len() == 10 && bytes[4] == b'-' && bytes[7] == b'-' &&
+    date.chars().enumerate().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        _ => c.is_digit(10),
+    })
+}
+
+/// The kind of comparison a date match option performs against the rustc
+/// build date
+#[derive(Clone, Copy)]
+enum DateCmp {
+    Min,
+    Max,
+    Exact,
+}
+
+/// Does a string match a glob pattern where `*` matches any run of
+/// characters? The match is anchored at both the start and end of the
+/// string.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(&'*') => {
+            glob_match(&pattern[1..], text) ||
+            (!text.is_empty() && glob_match(pattern, &text[1..]))
+        }
+        Some(&c) => !text.is_empty() && text[0] == c && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// The rustc executable to probe, honoring a `RUSTC` environment override
+fn rustc_executable() -> OsString {
+    env::var_os("RUSTC").unwrap_or_else(|| OsString::from("rustc"))
+}
+
+/// The cargo executable to run the chained command with, honoring a
+/// `CARGO` environment override
+fn cargo_executable() -> OsString {
+    env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"))
+}
+
+/// If the first argument following `when`/`unless` is a rustup-style
+/// `+toolchain` override, pulls it out of the argument list so it isn't
+/// seen as the external cargo subcommand, returning the remaining
+/// arguments and the toolchain override, if any.
+fn split_toolchain(mut args: Vec<String>) -> (Vec<String>, Option<String>) {
+    let toolchain_index = args.get(1)
+        .filter(|sub| sub.as_str() == "when" || sub.as_str() == "unless")
+        .and_then(|_| args.get(2))
+        .filter(|arg| arg.len() > 1 && arg.starts_with('+'))
+        .map(|_| 2);
+
+    match toolchain_index {
+        Some(i) => {
+            let toolchain = args.remove(i);
+            (args, Some(toolchain))
+        }
+        None => (args, None),
+    }
+}
+
 /// Information on the rustc compiler version in this environment
 struct RustCInfo {
     channel: String,
     version: Version,
+    date: Option<String>,
+    host: String,
+    cfg: HashSet<Cfg>,
 }
 
 impl RustCInfo {
-    /// Obtains the rust compiler info
-    fn get_info() -> RustCInfo {
-        // Get RustC version from command output
-        let output = Command::new("rustc")
-            .arg("-V")
+    /// Obtains the rust compiler info, optionally for a rustup `+toolchain`
+    /// override
+    fn get_info(toolchain: Option<&str>) -> RustCInfo {
+        // Get RustC version info from command output
+        let mut rustc = Command::new(rustc_executable());
+        if let Some(toolchain) = toolchain {
+            rustc.arg(toolchain);
+        }
+        let output = rustc.arg("-vV")
             .stdin(Stdio::null())
             .stderr(Stdio::inherit())
             .output()
             .expect("Failed to get rustc version");
         let output_str = String::from_utf8_lossy(&output.stdout);
 
-        // Parse the string and get the version portion
-        let verstr = output_str.split_whitespace()
-            .nth(1)
+        // Parse the string and get the version portion from the first line
+        let verstr = output_str.lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
             .expect("Failed to get rustc version string");
         let version = Version::parse(verstr).expect("Failed to parse rustc version");
 
+        // Get the commit date from the 'commit-date:' line, if present and valid
+        let date = output_str.lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.starts_with("commit-date:") {
+                    Some(line["commit-date:".len()..].trim().to_string())
+                } else {
+                    None
+                }
+            })
+            .next()
+            .and_then(|d| if is_valid_date(&d) { Some(d) } else { None });
+
+        // Get the host target triple from the 'host:' line
+        let host = output_str.lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                if line.starts_with("host:") {
+                    Some(line["host:".len()..].trim().to_string())
+                } else {
+                    None
+                }
+            })
+            .next()
+            .expect("Failed to get rustc host triple");
+
         // Get channel from pre-release portion of version
         let channel = match version.pre.iter().next() {
             Some(ident) => {
@@ -53,9 +320,29 @@ impl RustCInfo {
             build: vec![],
         };
 
+        // Get the compiler's cfg configuration
+        let mut rustc_cfg = Command::new(rustc_executable());
+        if let Some(toolchain) = toolchain {
+            rustc_cfg.arg(toolchain);
+        }
+        let cfg_output = rustc_cfg.arg("--print")
+            .arg("cfg")
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .expect("Failed to get rustc cfg");
+        let cfg_output_str = String::from_utf8_lossy(&cfg_output.stdout);
+        let cfg = cfg_output_str.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(parse_cfg_line)
+            .collect();
+
         RustCInfo {
             channel: channel,
             version: version,
+            date: date,
+            host: host,
+            cfg: cfg,
         }
     }
 
@@ -92,6 +379,116 @@ impl RustCInfo {
             None => Ok(true),
         }
     }
+
+    /// Does the rust compiler's cfg configuration satisfy a cfg-expression?
+    fn matches_cfg_expr(&self, expr: &CfgExpr) -> bool {
+        match *expr {
+            CfgExpr::Value(ref cfg) => self.cfg.contains(cfg),
+            CfgExpr::All(ref exprs) => exprs.iter().all(|e| self.matches_cfg_expr(e)),
+            CfgExpr::Any(ref exprs) => exprs.iter().any(|e| self.matches_cfg_expr(e)),
+            CfgExpr::Not(ref expr) => !self.matches_cfg_expr(expr),
+        }
+    }
+
+    /// Do any of the cfg-expression values match?
+    fn matches_any_cfgs<'a, 'b>(&'a self, cfgs: Option<Values<'b>>) -> Result<bool, String> {
+        match cfgs {
+            Some(exprs) => {
+                for e in exprs {
+                    let parsed = try!(CfgExpr::parse(e));
+                    if self.matches_cfg_expr(&parsed) {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Does the rustc build date satisfy a single date constraint? Builds
+    /// with an unknown commit date never match a date constraint.
+    fn matches_date(&self, date: &str, cmp: DateCmp) -> bool {
+        match self.date {
+            Some(ref d) => {
+                match cmp {
+                    DateCmp::Min => d.as_str() >= date,
+                    DateCmp::Max => d.as_str() <= date,
+                    DateCmp::Exact => d.as_str() == date,
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Do any of the date values match, given a comparison kind?
+    fn matches_any_dates<'a, 'b>(&'a self,
+                                  dates: Option<Values<'b>>,
+                                  cmp: DateCmp)
+                                  -> Result<bool, String> {
+        match dates {
+            Some(ds) => {
+                for d in ds {
+                    if !is_valid_date(d) {
+                        return Err(format!("Invalid date '{}', expected format YYYY-MM-DD", d));
+                    }
+                    if self.matches_date(d, cmp) {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Does a glob pattern match the compiler's host target triple?
+    fn matches_host(&self, pattern: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let host: Vec<char> = self.host.chars().collect();
+        glob_match(&pattern, &host)
+    }
+
+    /// Do any of the host glob patterns match?
+    fn matches_any_hosts<'a, 'b>(&'a self, hosts: Option<Values<'b>>) -> bool {
+        hosts.map_or(true, |mut hosts| hosts.any(|h| self.matches_host(h)))
+    }
+
+    /// Does a parsed `--expr` expression match the compiler and environment?
+    fn matches_expr(&self, expr: &MatchExpr) -> Result<bool, String> {
+        match *expr {
+            MatchExpr::Channel(ref channel) => Ok(self.matches_channel(channel)),
+            MatchExpr::Version(ref version) => {
+                self.matches_version(version).map_err(|e| e.to_string())
+            }
+            MatchExpr::Exists(ref name) => Ok(env::var_os(name).is_some()),
+            MatchExpr::Equals(ref name, ref value) => {
+                Ok(EnvVarReq {
+                        name: name,
+                        value: value,
+                    }
+                    .matches())
+            }
+            MatchExpr::Cfg(ref cfg) => Ok(self.matches_cfg_expr(cfg)),
+            MatchExpr::Not(ref expr) => self.matches_expr(expr).map(|b| !b),
+            MatchExpr::And(ref exprs) => {
+                for e in exprs {
+                    if !try!(self.matches_expr(e)) {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            MatchExpr::Or(ref exprs) => {
+                for e in exprs {
+                    if try!(self.matches_expr(e)) {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
 }
 
 /// A parsed environment variable requirement such as 'RUST_SRC_PATH=~/rustsrc'
@@ -147,10 +544,261 @@ fn matches_any_env_vars<'a>(vars: Option<Values<'a>>) -> Result<bool, String> {
     }
 }
 
+/// A parsed `--expr` boolean expression combining match predicates
+enum MatchExpr {
+    Channel(String),
+    Version(String),
+    Exists(String),
+    Equals(String, String),
+    Cfg(CfgExpr),
+    Not(Box<MatchExpr>),
+    And(Vec<MatchExpr>),
+    Or(Vec<MatchExpr>),
+}
+
+impl MatchExpr {
+    /// Parses a `--expr` string such as
+    /// `channel(nightly) or (channel(stable) and version(">=1.60"))`
+    fn parse(input: &str) -> Result<MatchExpr, String> {
+        ExprParser::new(input).parse()
+    }
+}
+
+/// Strips a single pair of surrounding double quotes, if present
+fn unquote(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        s[1..s.len() - 1].to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Recursive-descent parser for the `--expr` grammar: `not` binds tighter
+/// than `and`, which binds tighter than `or`, and parentheses group
+/// sub-expressions around the `channel(...)`, `version(...)`, `exists(...)`,
+/// `equals(...)` and `cfg(...)` predicate atoms.
+struct ExprParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn new(input: &str) -> ExprParser {
+        ExprParser {
+            chars: input.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<MatchExpr, String> {
+        let expr = try!(self.parse_or());
+        self.skip_whitespace();
+        if self.pos != self.chars.len() {
+            return Err(format!("Unexpected trailing characters in expression at position {}",
+                                self.pos));
+        }
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}' at position {} in expression", c, self.pos))
+        }
+    }
+
+    /// Consumes a keyword (`and`/`or`/`not`) if it appears next, respecting
+    /// word boundaries so e.g. `notify(...)` isn't parsed as `not ify(...)`
+    fn consume_keyword(&mut self, kw: &str) -> bool {
+        let save = self.pos;
+        self.skip_whitespace();
+        for c in kw.chars() {
+            if self.peek() == Some(c) {
+                self.pos += 1;
+            } else {
+                self.pos = save;
+                return false;
+            }
+        }
+        if self.peek().map_or(false, |c| c.is_alphanumeric() || c == '_') {
+            self.pos = save;
+            return false;
+        }
+        true
+    }
+
+    fn parse_or(&mut self) -> Result<MatchExpr, String> {
+        let mut list = vec![try!(self.parse_and())];
+        while self.consume_keyword("or") {
+            list.push(try!(self.parse_and()));
+        }
+        Ok(if list.len() == 1 {
+            list.pop().unwrap()
+        } else {
+            MatchExpr::Or(list)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<MatchExpr, String> {
+        let mut list = vec![try!(self.parse_not())];
+        while self.consume_keyword("and") {
+            list.push(try!(self.parse_not()));
+        }
+        Ok(if list.len() == 1 {
+            list.pop().unwrap()
+        } else {
+            MatchExpr::And(list)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<MatchExpr, String> {
+        if self.consume_keyword("not") {
+            let expr = try!(self.parse_not());
+            Ok(MatchExpr::Not(Box::new(expr)))
+        } else {
+            self.parse_atom()
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<MatchExpr, String> {
+        self.skip_whitespace();
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let expr = try!(self.parse_or());
+            try!(self.expect(')'));
+            Ok(expr)
+        } else {
+            self.parse_predicate()
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(format!("Expected identifier at position {} in expression", start));
+        }
+        Ok(self.chars[start..self.pos].iter().cloned().collect())
+    }
+
+    /// Captures the raw text of a predicate argument up to its closing
+    /// paren, honoring nested parens and quoted strings so e.g. a `cfg(...)`
+    /// argument can itself contain `all(...)`/`not(...)` and commas.
+    fn parse_raw_arg(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                None => return Err("Unexpected end of expression inside predicate argument"
+                    .to_string()),
+                Some('"') => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        self.pos += 1;
+                        if c == '"' {
+                            break;
+                        }
+                    }
+                }
+                Some('(') => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                Some(')') if depth == 0 => break,
+                Some(')') => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+        Ok(self.chars[start..self.pos].iter().cloned().collect())
+    }
+
+    fn parse_predicate(&mut self) -> Result<MatchExpr, String> {
+        let name = try!(self.parse_ident());
+        try!(self.expect('('));
+        let arg = try!(self.parse_raw_arg());
+        try!(self.expect(')'));
+        match name.as_str() {
+            "channel" => Ok(MatchExpr::Channel(arg.trim().to_lowercase())),
+            "version" => Ok(MatchExpr::Version(unquote(arg.trim()))),
+            "exists" => Ok(MatchExpr::Exists(arg.trim().to_string())),
+            "equals" => {
+                let req = try!(EnvVarReq::parse(arg.trim()));
+                Ok(MatchExpr::Equals(req.name.to_string(), unquote(req.value)))
+            }
+            "cfg" => Ok(MatchExpr::Cfg(try!(CfgExpr::parse(arg.trim())))),
+            _ => Err(format!("Unknown predicate '{}' in expression", name)),
+        }
+    }
+}
+
 /// Do all the command line options match?
-fn options_match<'a>(sub: &'a ArgMatches<'a>) -> bool {
+fn options_match<'a>(sub: &'a ArgMatches<'a>, toolchain: Option<&str>) -> bool {
     // Query rustc version
-    let rustc_info = RustCInfo::get_info();
+    let rustc_info = RustCInfo::get_info(toolchain);
+
+    // The --expr mode is mutually exclusive with the flag-based options below, and
+    // fully replaces their AND/OR semantics with an explicit boolean expression. This
+    // is enforced manually rather than via clap's conflicts_with_all, since clap 2.34
+    // collapses an entire "multiple" ArgGroup to a single mutually-exclusive choice as
+    // soon as one member declares a conflict with other members of the same group.
+    if let Some(expr_str) = sub.value_of("EXPR") {
+        if sub.values_of("CHANNEL").is_some() || sub.values_of("VERSION").is_some() ||
+           sub.values_of("ENV-VARIABLE").is_some() ||
+           sub.values_of("ENV-VARIABLE=VALUE").is_some() ||
+           sub.values_of("CFG").is_some() || sub.values_of("MIN-DATE").is_some() ||
+           sub.values_of("MAX-DATE").is_some() || sub.values_of("DATE").is_some() ||
+           sub.values_of("HOST").is_some() {
+            writeln!(std::io::stderr(),
+                     "--expr cannot be used together with the other match options")
+                .ok();
+            writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+            exit(1);
+        }
+
+        let expr = match MatchExpr::parse(expr_str) {
+            Ok(expr) => expr,
+            Err(e) => {
+                writeln!(std::io::stderr(), "{}", e).ok();
+                writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+                exit(1);
+            }
+        };
+        return match rustc_info.matches_expr(&expr) {
+            Ok(b) => b,
+            Err(e) => {
+                writeln!(std::io::stderr(), "{}", e).ok();
+                writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+                exit(1);
+            }
+        };
+    }
 
     // Do all the provided match options match the current compiler and environment?
     let env_matches = matches_any_env_vars(sub.values_of("ENV-VARIABLE=VALUE"));
@@ -167,15 +815,50 @@ fn options_match<'a>(sub: &'a ArgMatches<'a>) -> bool {
         exit(1);
     }
 
+    let cfg_matches = rustc_info.matches_any_cfgs(sub.values_of("CFG"));
+    if cfg_matches.is_err() {
+        writeln!(std::io::stderr(), "{}", cfg_matches.unwrap_err()).ok();
+        writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+        exit(1);
+    }
+
+    let min_date_matches = rustc_info.matches_any_dates(sub.values_of("MIN-DATE"), DateCmp::Min);
+    if min_date_matches.is_err() {
+        writeln!(std::io::stderr(), "{}", min_date_matches.unwrap_err()).ok();
+        writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+        exit(1);
+    }
+
+    let max_date_matches = rustc_info.matches_any_dates(sub.values_of("MAX-DATE"), DateCmp::Max);
+    if max_date_matches.is_err() {
+        writeln!(std::io::stderr(), "{}", max_date_matches.unwrap_err()).ok();
+        writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+        exit(1);
+    }
+
+    let date_matches = rustc_info.matches_any_dates(sub.values_of("DATE"), DateCmp::Exact);
+    if date_matches.is_err() {
+        writeln!(std::io::stderr(), "{}", date_matches.unwrap_err()).ok();
+        writeln!(std::io::stderr(), "{}", sub.usage()).ok();
+        exit(1);
+    }
+
     rustc_info.matches_any_channels(sub.values_of("CHANNEL")) && vers_matches.unwrap() &&
-    any_env_vars_exist(sub.values_of_os("ENV-VARIABLE")) && env_matches.unwrap()
+    any_env_vars_exist(sub.values_of_os("ENV-VARIABLE")) && env_matches.unwrap() &&
+    cfg_matches.unwrap() && min_date_matches.unwrap() && max_date_matches.unwrap() &&
+    date_matches.unwrap() && rustc_info.matches_any_hosts(sub.values_of("HOST"))
 }
 
-/// Get the cargo command and arguments
-fn get_cargo_command<'a>(sub: &'a ArgMatches<'a>) -> Vec<Cow<'a, OsStr>> {
+/// Get the cargo command and arguments, forwarding the `+toolchain`
+/// override, if any, so the executed command matches the one probed
+fn get_cargo_command<'a>(sub: &'a ArgMatches<'a>, toolchain: Option<&'a str>) -> Vec<Cow<'a, OsStr>> {
     match sub.subcommand() {
         (external, Some(extm)) => {
-            let mut cmd: Vec<Cow<'a, OsStr>> = vec![Cow::Owned(From::from(external))];
+            let mut cmd: Vec<Cow<'a, OsStr>> = Vec::new();
+            if let Some(toolchain) = toolchain {
+                cmd.push(Cow::Borrowed(OsStr::new(toolchain)));
+            }
+            cmd.push(Cow::Owned(From::from(external)));
             if let Some(vals) = extm.values_of_os("") {
                 cmd.extend(vals.map(|s| Cow::Borrowed(s)));
             }
@@ -190,6 +873,11 @@ fn get_cargo_command<'a>(sub: &'a ArgMatches<'a>) -> Vec<Cow<'a, OsStr>> {
 }
 
 fn main() {
+    // Pull a leading rustup-style '+toolchain' override out of the args before
+    // handing them to clap, so it isn't mistaken for the external subcommand
+    let (argv, toolchain) = split_toolchain(env::args().collect());
+    let toolchain = toolchain.as_ref().map(|s| s.as_str());
+
     // CLI
     let matches = App::new("cargo when")
                     .bin_name("cargo")
@@ -199,7 +887,7 @@ fn main() {
                     .setting(AppSettings::SubcommandRequiredElseHelp)
                     .setting(AppSettings::GlobalVersion)
                     .subcommand(SubCommand::with_name("when")
-                        .usage("cargo when [OPTIONS] <CARGO SUBCOMMAND> [SUBCOMMAND OPTIONS]")
+                        .usage("cargo when [+TOOLCHAIN] [OPTIONS] <CARGO SUBCOMMAND> [SUBCOMMAND OPTIONS]")
                         .about(concat!("Runs subsequent cargo command only when the specified ",
                                         "options match the current rust compiler version and ",
                                         "environment."))
@@ -208,7 +896,10 @@ fn main() {
                                             "spaces. At least one match option is required. If ",
                                             "multiple match options are present, each option ",
                                             "specifies an additional match requirement for any of ",
-                                            "the set of possible values for that option."))
+                                            "the set of possible values for that option.\n\nA ",
+                                            "rustup-style +toolchain override, e.g. '+nightly', may ",
+                                            "be given as the first argument to probe and run that ",
+                                            "toolchain instead of the default one."))
                         .setting(AppSettings::ArgRequiredElseHelp)
                         .setting(AppSettings::AllowExternalSubcommands)
                         .group(ArgGroup::with_name("matches")
@@ -254,11 +945,70 @@ fn main() {
                             .min_values(1)
                             .require_delimiter(true)
                         )
+                        .arg(Arg::with_name("CFG")
+                            .long("cfg")
+                            .group("matches")
+                            .help(concat!("Matches a cfg-expression against `rustc --print cfg`, ",
+                                            "e.g. 'all(target_os = \"linux\", not(target_feature = ",
+                                            "\"crt-static\"))'. May be repeated to OR multiple ",
+                                            "expressions together."))
+                            .takes_value(true)
+                            .min_values(1)
+                            .multiple(true)
+                            .number_of_values(1)
+                        )
+                        .arg(Arg::with_name("MIN-DATE")
+                            .long("min-date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date is on or after the ",
+                                            "given date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("MAX-DATE")
+                            .long("max-date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date is on or before the ",
+                                            "given date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("DATE")
+                            .long("date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date equals the given ",
+                                            "date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("HOST")
+                            .long("host")
+                            .group("matches")
+                            .help(concat!("Matches rustc's host target triple(s), supporting '*' ",
+                                            "globs, e.g. '*-linux-*' or '*-pc-windows-*'"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("EXPR")
+                            .long("expr")
+                            .group("matches")
+                            .help(concat!("Matches an explicit boolean expression combining ",
+                                            "channel(...), version(...), exists(...), ",
+                                            "equals(...) and cfg(...) predicates with and/or/not ",
+                                            "and parentheses, e.g. 'channel(nightly) or ",
+                                            "(channel(stable) and version(\">=1.60\"))'. Mutually ",
+                                            "exclusive with the other match options."))
+                            .takes_value(true)
+                        )
                     )
                     // We don't use an alias even though args ar exact same because help is slightly
                     // different and the help won't properly show 'unless' when used.
                     .subcommand(SubCommand::with_name("unless")
-                        .usage("cargo unless [OPTIONS] <CARGO SUBCOMMAND> [SUBCOMMAND OPTIONS]")
+                        .usage("cargo unless [+TOOLCHAIN] [OPTIONS] <CARGO SUBCOMMAND> [SUBCOMMAND OPTIONS]")
                         .about(concat!("Runs subsequent cargo command except when the specified ",
                                         "options match the current rust compiler version and ",
                                         "environment. This is the negation of 'cargo when'."))
@@ -267,7 +1017,10 @@ fn main() {
                                             "spaces. At least one match option is required. If ",
                                             "multiple match options are present, each option ",
                                             "specifies an additional match requirement for any of ",
-                                            "the set of possible values for that option."))
+                                            "the set of possible values for that option.\n\nA ",
+                                            "rustup-style +toolchain override, e.g. '+nightly', may ",
+                                            "be given as the first argument to probe and run that ",
+                                            "toolchain instead of the default one."))
                         .setting(AppSettings::ArgRequiredElseHelp)
                         .setting(AppSettings::AllowExternalSubcommands)
                         .group(ArgGroup::with_name("matches")
@@ -313,20 +1066,79 @@ fn main() {
                             .min_values(1)
                             .require_delimiter(true)
                         )
-                    ).get_matches();
+                        .arg(Arg::with_name("CFG")
+                            .long("cfg")
+                            .group("matches")
+                            .help(concat!("Matches a cfg-expression against `rustc --print cfg`, ",
+                                            "e.g. 'all(target_os = \"linux\", not(target_feature = ",
+                                            "\"crt-static\"))'. May be repeated to OR multiple ",
+                                            "expressions together."))
+                            .takes_value(true)
+                            .min_values(1)
+                            .multiple(true)
+                            .number_of_values(1)
+                        )
+                        .arg(Arg::with_name("MIN-DATE")
+                            .long("min-date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date is on or after the ",
+                                            "given date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("MAX-DATE")
+                            .long("max-date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date is on or before the ",
+                                            "given date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("DATE")
+                            .long("date")
+                            .group("matches")
+                            .help(concat!("Matches when rustc's commit-date equals the given ",
+                                            "date(s), in YYYY-MM-DD format"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("HOST")
+                            .long("host")
+                            .group("matches")
+                            .help(concat!("Matches rustc's host target triple(s), supporting '*' ",
+                                            "globs, e.g. '*-linux-*' or '*-pc-windows-*'"))
+                            .takes_value(true)
+                            .min_values(1)
+                            .require_delimiter(true)
+                        )
+                        .arg(Arg::with_name("EXPR")
+                            .long("expr")
+                            .group("matches")
+                            .help(concat!("Matches an explicit boolean expression combining ",
+                                            "channel(...), version(...), exists(...), ",
+                                            "equals(...) and cfg(...) predicates with and/or/not ",
+                                            "and parentheses, e.g. 'channel(nightly) or ",
+                                            "(channel(stable) and version(\">=1.60\"))'. Mutually ",
+                                            "exclusive with the other match options."))
+                            .takes_value(true)
+                        )
+                    ).get_matches_from(argv);
 
     // Check conditions, gets command if matches, None if not
     let command = match matches.subcommand() {
         ("when", Some(sub)) => {
-            if options_match(sub) {
-                Some(get_cargo_command(sub))
+            if options_match(sub, toolchain) {
+                Some(get_cargo_command(sub, toolchain))
             } else {
                 None
             }
         }
         ("unless", Some(sub)) => {
-            if !options_match(sub) {
-                Some(get_cargo_command(sub))
+            if !options_match(sub, toolchain) {
+                Some(get_cargo_command(sub, toolchain))
             } else {
                 None
             }
@@ -336,7 +1148,7 @@ fn main() {
 
     // If we're a match, the chained cargo command will be provided, otherwise, we do nothing
     if let Some(args) = command {
-        let status = Command::new("cargo")
+        let status = Command::new(cargo_executable())
             .args(&args)
             .stdin(Stdio::inherit())
             .stdout(Stdio::inherit())